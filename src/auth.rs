@@ -0,0 +1,115 @@
+//! Twitch-backed authorization flow for 7TV, so callers don't have to dig a
+//! `seventv-auth` cookie out of their browser by hand.
+
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::net::TcpListener;
+
+const AUTHORIZE_URL: &str = "https://7tv.io/v3/auth?platform=TWITCH";
+
+#[derive(Debug, Error)]
+pub enum SevenTvAuthError {
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("local callback listener never received a token")]
+    CallbackMissed,
+}
+
+/// A 7TV session token, as stored in the `seventv-auth` cookie, along with
+/// when it should be considered stale enough to warrant re-authenticating.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Token {
+    pub value: String,
+    pub expires_at: u64,
+}
+
+impl Token {
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Self::now() >= self.expires_at
+    }
+
+    fn path() -> Option<PathBuf> {
+        Some(dirs::cache_dir()?.join("emote_shuffler").join("token.json"))
+    }
+
+    pub fn load() -> Option<Self> {
+        let path = Self::path()?;
+        let data = std::fs::read(path).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+}
+
+/// Runs the authorization flow end-to-end: prints the URL the user needs to
+/// open (7TV handles the Twitch handshake itself), waits on a local
+/// callback listener for the resulting session cookie, then persists it.
+pub async fn login() -> Result<Token, SevenTvAuthError> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+
+    println!(
+        "Open the following URL to authorize emote_shuffler, then return here:\n\n  {AUTHORIZE_URL}&callback=http://127.0.0.1:{port}/callback\n"
+    );
+
+    let token = wait_for_callback(listener).await?;
+    let _ = token.save();
+    Ok(token)
+}
+
+async fn wait_for_callback(listener: TcpListener) -> Result<Token, SevenTvAuthError> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (mut stream, _) = listener.accept().await?;
+    let mut buf = [0u8; 4096];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+
+    let token_value = request
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|path| path.split("token=").nth(1))
+        .and_then(|rest| rest.split(['&', ' ']).next())
+        .map(str::to_owned)
+        .ok_or(SevenTvAuthError::CallbackMissed)?;
+
+    let body = "you may close this tab";
+    stream
+        .write_all(
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            )
+            .as_bytes(),
+        )
+        .await?;
+
+    Ok(Token {
+        value: token_value,
+        // 7TV session cookies are valid for a week; refreshed on next expired login().
+        expires_at: Token::now() + 60 * 60 * 24 * 7,
+    })
+}
@@ -0,0 +1,24 @@
+use clap::{Parser, Subcommand};
+
+#[derive(Debug, Parser)]
+#[command(author, version, about)]
+pub struct Args {
+    /// Twitch username whose emote set to operate on
+    pub username: String,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Shuffle the emote set's names
+    Shuffle,
+    /// Restore emote names from a previous shuffle
+    Restore {
+        /// id of the shuffle to restore, as shown by `history`
+        shuffle_id: ulid::Ulid,
+    },
+    /// List previous shuffles recorded for this emote set
+    History,
+}
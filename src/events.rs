@@ -0,0 +1,231 @@
+//! Live subscription to 7TV's EventAPI, for watching an emote set for
+//! changes instead of shuffling it once and exiting.
+
+use std::time::Duration;
+
+use futures_util::{SinkExt, Stream, StreamExt};
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::{net::TcpStream, sync::mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_tungstenite::{
+    tungstenite::{self, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::ObjectID;
+
+const EVENTAPI_ENDPOINT: &str = "wss://events.7tv.io/v3";
+/// 7TV sends a heartbeat at a steady cadence; if we go this long without
+/// seeing *any* message, the connection is considered stalled.
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(45);
+
+#[derive(Debug, Error)]
+pub enum SevenTvEventError {
+    #[error(transparent)]
+    WebSocket(#[from] tungstenite::Error),
+    #[error("failed to deserialize event payload: {0}")]
+    Deserialize(#[from] serde_json::Error),
+}
+
+/// A single change to an emote set, as pushed by the EventAPI.
+#[derive(Debug, Clone)]
+pub enum EmoteSetEvent {
+    EmoteAdded { emote_id: ObjectID, name: String },
+    EmoteRemoved { emote_id: ObjectID, name: String },
+    EmoteRenamed {
+        emote_id: ObjectID,
+        old_name: String,
+        new_name: String,
+    },
+}
+
+// 7TV EventAPI opcodes, see https://github.com/SevenTV/EventAPI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum Opcode {
+    Dispatch = 0,
+    Reconnect = 4,
+    EndOfStream = 7,
+}
+
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    op: u8,
+    #[serde(default)]
+    d: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct DispatchBody {
+    #[serde(rename = "type")]
+    kind: String,
+    body: ChangeMap,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangeMap {
+    #[serde(default)]
+    pushed: Vec<ChangeField>,
+    #[serde(default)]
+    pulled: Vec<ChangeField>,
+    #[serde(default)]
+    updated: Vec<ChangeField>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangeField {
+    value: ChangeValue,
+    #[serde(default)]
+    old_value: Option<ChangeValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangeValue {
+    id: ObjectID,
+    name: String,
+}
+
+enum ParsedMessage {
+    Dispatch(Vec<EmoteSetEvent>),
+    /// Server asked us to reconnect (`RECONNECT`) or hung up on its own
+    /// terms (`END_OF_STREAM`) -- either way, the current socket is done.
+    Reconnect,
+    /// Heartbeat, ack, or anything else we don't act on.
+    Other,
+}
+
+/// Opens a persistent connection to the EventAPI and subscribes to
+/// `emote_set.update` dispatches for `set_id`, reconnecting on drop, a
+/// stalled connection (no message within [`HEARTBEAT_TIMEOUT`]), or a
+/// server-initiated `RECONNECT`/`END_OF_STREAM` opcode.
+///
+/// Disconnects and failed reconnect attempts are retried internally, with a
+/// backoff doubling from 1s up to 30s; the stream does not end on its own.
+/// Once the returned stream is dropped, the background task stops the next
+/// time it tries to forward an event or to reconnect.
+pub async fn watch_emote_set(
+    set_id: ObjectID,
+) -> Result<impl Stream<Item = EmoteSetEvent>, SevenTvEventError> {
+    let (tx, rx) = mpsc::channel(32);
+    let socket = connect_and_subscribe(set_id).await?;
+    tokio::spawn(run_connection(set_id, socket, tx));
+    Ok(ReceiverStream::new(rx))
+}
+
+async fn connect_and_subscribe(
+    set_id: ObjectID,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, SevenTvEventError> {
+    let (mut socket, _) = tokio_tungstenite::connect_async(EVENTAPI_ENDPOINT).await?;
+
+    let subscribe = serde_json::json!({
+        "op": 35, // SUBSCRIBE
+        "d": {
+            "type": "emote_set.update",
+            "condition": { "object_id": set_id },
+        }
+    });
+    socket
+        .send(Message::Text(subscribe.to_string()))
+        .await?;
+
+    Ok(socket)
+}
+
+async fn run_connection(
+    set_id: ObjectID,
+    mut socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    tx: mpsc::Sender<EmoteSetEvent>,
+) {
+    loop {
+        let message = match tokio::time::timeout(HEARTBEAT_TIMEOUT, socket.next()).await {
+            Ok(Some(Ok(message))) => message,
+            // transport error, clean close, or no message within the
+            // heartbeat deadline: the connection is dead either way.
+            Ok(Some(Err(_))) | Ok(None) | Err(_) => break,
+        };
+
+        match message {
+            Message::Text(text) => match parse_message(&text) {
+                ParsedMessage::Dispatch(events) => {
+                    for event in events {
+                        if tx.send(event).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                ParsedMessage::Reconnect => break,
+                ParsedMessage::Other => {}
+            },
+            Message::Ping(payload) => {
+                let _ = socket.send(Message::Pong(payload)).await;
+            }
+            _ => {}
+        }
+    }
+
+    // the connection dropped (heartbeat timeout, RECONNECT/END_OF_STREAM, or
+    // a transport error) -- reconnect with backoff and keep forwarding into
+    // the same channel, unless the receiving end has gone away.
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        if tx.is_closed() {
+            return;
+        }
+        match connect_and_subscribe(set_id).await {
+            Ok(socket) => {
+                Box::pin(run_connection(set_id, socket, tx)).await;
+                return;
+            }
+            Err(_) => {
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(Duration::from_secs(30));
+            }
+        }
+    }
+}
+
+fn parse_message(text: &str) -> ParsedMessage {
+    let Ok(envelope) = serde_json::from_str::<Envelope>(text) else {
+        return ParsedMessage::Other;
+    };
+
+    if envelope.op == Opcode::Reconnect as u8 || envelope.op == Opcode::EndOfStream as u8 {
+        return ParsedMessage::Reconnect;
+    }
+    if envelope.op != Opcode::Dispatch as u8 {
+        return ParsedMessage::Other;
+    }
+
+    let Ok(body) = serde_json::from_value::<DispatchBody>(envelope.d) else {
+        return ParsedMessage::Other;
+    };
+    if body.kind != "emote_set.update" {
+        return ParsedMessage::Other;
+    }
+
+    let mut events = Vec::new();
+    for field in body.body.pushed {
+        events.push(EmoteSetEvent::EmoteAdded {
+            emote_id: field.value.id,
+            name: field.value.name,
+        });
+    }
+    for field in body.body.pulled {
+        events.push(EmoteSetEvent::EmoteRemoved {
+            emote_id: field.value.id,
+            name: field.value.name,
+        });
+    }
+    for field in body.body.updated {
+        if let Some(old) = field.old_value {
+            events.push(EmoteSetEvent::EmoteRenamed {
+                emote_id: field.value.id,
+                old_name: old.name,
+                new_name: field.value.name,
+            });
+        }
+    }
+
+    ParsedMessage::Dispatch(events)
+}
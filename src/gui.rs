@@ -0,0 +1,158 @@
+//! Optional windowed front-end for the emote shuffler, gated behind the
+//! `gui` feature. Wraps [`SevenTvGqlClient`] instead of reimplementing any
+//! of its networking, so both this and the CLI in `main.rs` share the same
+//! core logic.
+
+use eframe::egui;
+use tokio::sync::mpsc;
+
+use crate::{ShuffleProgress, SevenTvGqlClient};
+
+pub fn run(client: SevenTvGqlClient) -> eframe::Result<()> {
+    eframe::run_native(
+        "emote shuffler",
+        eframe::NativeOptions::default(),
+        Box::new(|_cc| Box::new(EmoteShufflerApp::new(client))),
+    )
+}
+
+enum Stage {
+    EnterUsername,
+    Loading,
+    Preview,
+    Shuffling { done: usize, total: usize },
+    Done,
+    Error(String),
+}
+
+struct EmoteShufflerApp {
+    client: std::sync::Arc<SevenTvGqlClient>,
+    rt: tokio::runtime::Handle,
+    username: String,
+    set: Option<crate::get_emote_set::GetEmoteSetEmoteSet>,
+    stage: Stage,
+    progress_rx: Option<mpsc::Receiver<ShuffleProgress>>,
+    fetch_rx: Option<mpsc::Receiver<Result<crate::get_emote_set::GetEmoteSetEmoteSet, String>>>,
+}
+
+impl EmoteShufflerApp {
+    fn new(client: SevenTvGqlClient) -> Self {
+        Self {
+            client: std::sync::Arc::new(client),
+            rt: tokio::runtime::Handle::current(),
+            username: String::new(),
+            set: None,
+            stage: Stage::EnterUsername,
+            progress_rx: None,
+            fetch_rx: None,
+        }
+    }
+
+    fn fetch_set(&mut self) {
+        let client = self.client.clone();
+        let username = self.username.clone();
+        let (tx, rx) = mpsc::channel(1);
+        self.fetch_rx = Some(rx);
+        self.stage = Stage::Loading;
+        self.rt.spawn(async move {
+            let result = client
+                .get_user_emote_set(username)
+                .await
+                .map_err(|e| e.to_string());
+            let _ = tx.send(result).await;
+        });
+    }
+
+    fn poll_fetch(&mut self) {
+        let Some(rx) = &mut self.fetch_rx else {
+            return;
+        };
+        let Ok(result) = rx.try_recv() else {
+            return;
+        };
+        match result {
+            Ok(set) => {
+                self.set = Some(set);
+                self.stage = Stage::Preview;
+            }
+            Err(e) => self.stage = Stage::Error(e),
+        }
+        self.fetch_rx = None;
+    }
+
+    fn start_shuffle(&mut self) {
+        let Some(set) = &self.set else { return };
+        let set_id = set.id;
+        let client = self.client.clone();
+        let (tx, rx) = mpsc::channel(16);
+        self.progress_rx = Some(rx);
+        self.stage = Stage::Shuffling { done: 0, total: 0 };
+        self.rt.spawn(async move {
+            let _ = client.shuffle_set_with_progress(set_id, tx).await;
+        });
+    }
+
+    fn poll_progress(&mut self) {
+        let Some(rx) = &mut self.progress_rx else {
+            return;
+        };
+        while let Ok(msg) = rx.try_recv() {
+            match msg {
+                ShuffleProgress::Started { total } => self.stage = Stage::Shuffling { done: 0, total },
+                ShuffleProgress::Renamed { done, total, .. } => {
+                    self.stage = Stage::Shuffling { done, total }
+                }
+                ShuffleProgress::Finished => self.stage = Stage::Done,
+            }
+        }
+    }
+}
+
+impl eframe::App for EmoteShufflerApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_progress();
+        self.poll_fetch();
+
+        egui::CentralPanel::default().show(ctx, |ui| match &self.stage {
+            Stage::EnterUsername => {
+                ui.label("Twitch username:");
+                ui.text_edit_singleline(&mut self.username);
+                if ui.button("Load emote set").clicked() {
+                    self.fetch_set();
+                }
+            }
+            Stage::Loading => {
+                ui.label("Loading emote set...");
+            }
+            Stage::Preview => {
+                if let Some(set) = &self.set {
+                    egui::Grid::new("emote_grid").show(ui, |ui| {
+                        for emote in &set.emotes {
+                            ui.label(&emote.name);
+                            ui.end_row();
+                        }
+                    });
+                }
+                if ui.button("Shuffle!").clicked() {
+                    self.start_shuffle();
+                }
+            }
+            Stage::Shuffling { done, total } => {
+                let progress = if *total == 0 {
+                    0.0
+                } else {
+                    *done as f32 / *total as f32
+                };
+                ui.add(egui::ProgressBar::new(progress).text(format!("{done}/{total}")));
+            }
+            Stage::Done => {
+                ui.label("Done shuffling!");
+            }
+            Stage::Error(err) => {
+                ui.colored_label(egui::Color32::RED, err);
+            }
+        });
+
+        ctx.request_repaint();
+    }
+}
@@ -0,0 +1,226 @@
+//! Persistence for past shuffles, so a `shuffle_set` call can be undone
+//! with [`crate::SevenTvGqlClient::restore`] instead of being a one-shot,
+//! irreversible operation.
+
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    FromRow, SqlitePool,
+};
+use thiserror::Error;
+
+use crate::ObjectID;
+
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+    #[error("no shuffle with id {0} found for this set")]
+    ShuffleNotFound(ulid::Ulid),
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ShuffleRecord {
+    pub shuffle_id: String,
+    pub set_id: String,
+    pub created_at: i64,
+}
+
+/// A single not-yet-applied rename from a shuffle's plan, as persisted so
+/// an interrupted run can resume without recomputing (and thus changing)
+/// the permutation.
+#[derive(Debug, Clone, FromRow)]
+pub struct PendingOp {
+    pub op_index: i64,
+    pub cycle_index: i64,
+    pub emote_id: String,
+    pub target_name: String,
+}
+
+pub struct HistoryStore {
+    pool: SqlitePool,
+}
+
+impl HistoryStore {
+    pub fn default_path() -> Option<PathBuf> {
+        Some(dirs::data_dir()?.join("emote_shuffler").join("history.db"))
+    }
+
+    pub async fn open(path: impl AsRef<std::path::Path>) -> Result<Self, HistoryError> {
+        if let Some(parent) = path.as_ref().parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        // `shuffle_set` can have up to `MAX_CONCURRENT_CYCLES` workers writing
+        // to this same file at once; WAL mode plus a busy timeout lets those
+        // writes queue instead of failing outright with `SQLITE_BUSY`.
+        let options = SqliteConnectOptions::new()
+            .filename(path.as_ref())
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(Duration::from_secs(5));
+
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS shuffles (
+                shuffle_id TEXT NOT NULL,
+                set_id TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                PRIMARY KEY (shuffle_id, set_id)
+            )",
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS shuffle_renames (
+                shuffle_id TEXT NOT NULL,
+                set_id TEXT NOT NULL,
+                emote_id TEXT NOT NULL,
+                original_name TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS shuffle_ops (
+                shuffle_id TEXT NOT NULL,
+                set_id TEXT NOT NULL,
+                op_index INTEGER NOT NULL,
+                cycle_index INTEGER NOT NULL,
+                emote_id TEXT NOT NULL,
+                target_name TEXT NOT NULL,
+                done INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (shuffle_id, set_id, op_index)
+            )",
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self { pool })
+    }
+
+    /// Records the original `emote_id -> name` mapping for a set, plus the
+    /// full rename plan (grouped by independent cycle) needed to realize a
+    /// shuffle, all before any rename is issued.
+    pub async fn record_shuffle(
+        &self,
+        set_id: ObjectID,
+        shuffle_id: ulid::Ulid,
+        original_names: &HashMap<ObjectID, String>,
+        cycles: &[Vec<(ObjectID, String)>],
+    ) -> Result<(), HistoryError> {
+        sqlx::query("INSERT INTO shuffles (shuffle_id, set_id, created_at) VALUES (?, ?, ?)")
+            .bind(shuffle_id.to_string())
+            .bind(set_id.to_string())
+            .bind(crate::util::unix_timestamp())
+            .execute(&self.pool)
+            .await?;
+
+        for (emote_id, original_name) in original_names {
+            sqlx::query(
+                "INSERT INTO shuffle_renames (shuffle_id, set_id, emote_id, original_name) \
+                 VALUES (?, ?, ?, ?)",
+            )
+            .bind(shuffle_id.to_string())
+            .bind(set_id.to_string())
+            .bind(emote_id.to_string())
+            .bind(original_name)
+            .execute(&self.pool)
+            .await?;
+        }
+
+        let mut op_index = 0i64;
+        for (cycle_index, cycle) in cycles.iter().enumerate() {
+            for (emote_id, target_name) in cycle {
+                sqlx::query(
+                    "INSERT INTO shuffle_ops \
+                     (shuffle_id, set_id, op_index, cycle_index, emote_id, target_name, done) \
+                     VALUES (?, ?, ?, ?, ?, ?, 0)",
+                )
+                .bind(shuffle_id.to_string())
+                .bind(set_id.to_string())
+                .bind(op_index)
+                .bind(cycle_index as i64)
+                .bind(emote_id.to_string())
+                .bind(target_name)
+                .execute(&self.pool)
+                .await?;
+                op_index += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads the not-yet-applied ops for a shuffle, ordered the same way
+    /// they were planned, so a resumed run can pick up where it left off.
+    pub async fn pending_ops(
+        &self,
+        set_id: ObjectID,
+        shuffle_id: ulid::Ulid,
+    ) -> Result<Vec<PendingOp>, HistoryError> {
+        Ok(sqlx::query_as(
+            "SELECT op_index, cycle_index, emote_id, target_name FROM shuffle_ops \
+             WHERE shuffle_id = ? AND set_id = ? AND done = 0 ORDER BY op_index",
+        )
+        .bind(shuffle_id.to_string())
+        .bind(set_id.to_string())
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    pub async fn mark_op_done(
+        &self,
+        set_id: ObjectID,
+        shuffle_id: ulid::Ulid,
+        op_index: i64,
+    ) -> Result<(), HistoryError> {
+        sqlx::query(
+            "UPDATE shuffle_ops SET done = 1 \
+             WHERE shuffle_id = ? AND set_id = ? AND op_index = ?",
+        )
+        .bind(shuffle_id.to_string())
+        .bind(set_id.to_string())
+        .bind(op_index)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Loads the `emote_id -> original name` mapping recorded for a shuffle.
+    pub async fn get_shuffle(
+        &self,
+        set_id: ObjectID,
+        shuffle_id: ulid::Ulid,
+    ) -> Result<HashMap<ObjectID, String>, HistoryError> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT emote_id, original_name FROM shuffle_renames WHERE shuffle_id = ? AND set_id = ?",
+        )
+        .bind(shuffle_id.to_string())
+        .bind(set_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        if rows.is_empty() {
+            return Err(HistoryError::ShuffleNotFound(shuffle_id));
+        }
+
+        Ok(rows
+            .into_iter()
+            .map(|(id, name)| (id.parse().expect("stored emote ids are valid ULIDs"), name))
+            .collect())
+    }
+
+    pub async fn list_history(&self, set_id: ObjectID) -> Result<Vec<ShuffleRecord>, HistoryError> {
+        Ok(sqlx::query_as(
+            "SELECT shuffle_id, set_id, created_at FROM shuffles WHERE set_id = ? ORDER BY created_at DESC",
+        )
+        .bind(set_id.to_string())
+        .fetch_all(&self.pool)
+        .await?)
+    }
+}
@@ -1,20 +1,50 @@
 use std::{
     borrow::Cow,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
+    path::PathBuf,
     time::Duration,
 };
 
+use futures_util::stream::{FuturesUnordered, StreamExt};
 use graphql_client::{GraphQLQuery, Response};
 use indicatif::{ProgressBar, ProgressStyle};
 use log::debug;
-use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::time::MissedTickBehavior;
+use tokio::sync::{mpsc, OnceCell};
 use util::{gen_temp_name, shuffle_slice};
 
+pub mod auth;
 pub mod cli;
+pub mod events;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod history;
+pub mod rate_limit;
 pub mod util;
 
+use history::{HistoryError, HistoryStore, PendingOp, ShuffleRecord};
+use rate_limit::RateLimiter;
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls-tls-webpki-roots")))]
+compile_error!(
+    "emote_shuffler needs a TLS backend: enable either the `native-tls` (default) \
+     or `rustls-tls-webpki-roots` feature"
+);
+
+/// How many independent rename cycles are allowed in flight at once.
+const MAX_CONCURRENT_CYCLES: usize = 8;
+/// Renames per minute shared across all in-flight cycles.
+const RENAME_RATE_PER_MINUTE: u32 = 100;
+
+/// Progress updates emitted by [`SevenTvGqlClient::shuffle_set_with_progress`],
+/// for front-ends that can't just print to an [`indicatif::ProgressBar`].
+#[derive(Debug, Clone)]
+pub enum ShuffleProgress {
+    Started { total: usize },
+    Renamed { done: usize, total: usize, from: String, to: String },
+    Finished,
+}
+
 #[derive(Debug, Error)]
 pub enum SevenTvGqlError {
     #[error("queried user was not found")]
@@ -23,23 +53,69 @@ pub enum SevenTvGqlError {
     EmoteRenameFailed(Vec<graphql_client::Error>),
     #[error(transparent)]
     RequestError(#[from] reqwest::Error),
+    #[error("seventv-auth token is missing or expired, log in again")]
+    Unauthorized,
+    #[error(transparent)]
+    History(#[from] HistoryError),
 }
 
 pub struct SevenTvGqlClient {
     client: reqwest::Client,
     auth_token: String,
+    history: OnceCell<HistoryStore>,
+    limiter: OnceCell<RateLimiter>,
 }
 
 impl SevenTvGqlClient {
     const ENDPOINT: &str = "https://7tv.io/v3/gql";
 
     pub fn new(token: String) -> Self {
+        Self::with_client(reqwest::Client::new(), token)
+    }
+
+    /// Builds a client around a caller-provided [`reqwest::Client`] instead
+    /// of `reqwest::Client::new()`, so embedders that already standardize
+    /// on a TLS stack or connection pool aren't forced into ours.
+    pub fn with_client(client: reqwest::Client, token: String) -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client,
             auth_token: token,
+            history: OnceCell::new(),
+            limiter: OnceCell::new(),
         }
     }
 
+    async fn history(&self) -> Result<&HistoryStore, SevenTvGqlError> {
+        self.history
+            .get_or_try_init(|| async {
+                let path = HistoryStore::default_path()
+                    .unwrap_or_else(|| PathBuf::from("emote_shuffler_history.db"));
+                HistoryStore::open(path).await
+            })
+            .await
+            .map_err(SevenTvGqlError::from)
+    }
+
+    /// The rate limiter backing rename calls, shared across every
+    /// `execute_resumable` run on this client instead of spawning a fresh
+    /// refill task (and leaking the old one) per shuffle or restore.
+    async fn limiter(&self) -> &RateLimiter {
+        self.limiter
+            .get_or_init(|| async { RateLimiter::per_minute(RENAME_RATE_PER_MINUTE) })
+            .await
+    }
+
+    /// Runs the Twitch-backed 7TV authorization flow and builds a client
+    /// from the resulting session token, reusing a cached one if it hasn't
+    /// expired yet.
+    pub async fn login() -> Result<Self, crate::auth::SevenTvAuthError> {
+        let token = match crate::auth::Token::load() {
+            Some(token) if !token.is_expired() => token,
+            _ => crate::auth::login().await?,
+        };
+        Ok(Self::new(token.value))
+    }
+
     pub async fn get_user_emote_set(
         &self,
         username: impl Into<String>,
@@ -69,7 +145,7 @@ impl SevenTvGqlClient {
         let set_id = result
             .connections
             .into_iter()
-            .find(|c| c.platform == ConnectionPlatform::Twitch)
+            .find(|c| c.platform == get_user_active_emote_set::ConnectionPlatform::TWITCH)
             .and_then(|s| s.emote_set_id);
         if let Some(set_id) = set_id {
             self.get_emote_set(set_id).await
@@ -114,6 +190,10 @@ impl SevenTvGqlClient {
             .send()
             .await?;
 
+        if resp.status() == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(SevenTvGqlError::Unauthorized);
+        }
+
         let response_body: Response<emote_rename::ResponseData> = resp.json().await?;
 
         if let Some(errors) = response_body.errors {
@@ -123,74 +203,372 @@ impl SevenTvGqlClient {
     }
 
     pub async fn shuffle_set(&self, set_id: ObjectID) -> Result<(), SevenTvGqlError> {
-        let set = self.get_emote_set(set_id).await?;
+        self.shuffle_set_inner(set_id, None).await
+    }
 
-        let mut names: Vec<&str> = set.emotes.iter().map(|e| e.name.as_str()).collect();
-        if names.is_empty() {
+    /// Same as [`Self::shuffle_set`], but reports progress through `progress`
+    /// instead of printing an [`indicatif::ProgressBar`]. Intended for
+    /// front-ends (e.g. the `gui` feature) that drive their own widgets.
+    pub async fn shuffle_set_with_progress(
+        &self,
+        set_id: ObjectID,
+        progress: mpsc::Sender<ShuffleProgress>,
+    ) -> Result<(), SevenTvGqlError> {
+        self.shuffle_set_inner(set_id, Some(progress)).await
+    }
+
+    async fn shuffle_set_inner(
+        &self,
+        set_id: ObjectID,
+        progress: Option<mpsc::Sender<ShuffleProgress>>,
+    ) -> Result<(), SevenTvGqlError> {
+        let set = self.get_emote_set(set_id).await?;
+        let cycles = compute_shuffle_cycles(&set);
+        if cycles.is_empty() {
+            if let Some(tx) = &progress {
+                let _ = tx.send(ShuffleProgress::Finished).await;
+            }
             return Ok(());
         }
-        shuffle_slice(&mut names);
 
-        // target name is key, original name is value
-        let map: HashMap<&str, &str> = names
+        let shuffle_id = ulid::Ulid::new();
+        let original_names: HashMap<ObjectID, String> =
+            set.emotes.iter().map(|e| (e.id, e.name.clone())).collect();
+        let owned_cycles: Vec<Vec<(ObjectID, String)>> = cycles
             .iter()
-            .zip(&set.emotes)
-            .map(|(t, orig)| (*t, orig.name.as_str()))
+            .map(|cycle| {
+                cycle
+                    .iter()
+                    .map(|(id, target)| (*id, target.to_string()))
+                    .collect()
+            })
             .collect();
+        self.history()
+            .await?
+            .record_shuffle(set_id, shuffle_id, &original_names, &owned_cycles)
+            .await?;
 
-        // maps original name to id
-        let emotes: HashMap<&str, ObjectID> =
-            set.emotes.iter().map(|e| (e.name.as_str(), e.id)).collect();
+        self.execute_resumable(set_id, shuffle_id, progress).await
+    }
 
-        // (source id, target)
-        let mut ops: Vec<(ObjectID, Cow<'_, str>)> = Vec::with_capacity(map.len() + 1);
-        let mut renamed = HashSet::<&str>::with_capacity(map.len());
+    /// Continues a shuffle that was interrupted mid-run (e.g. the process
+    /// was killed), picking up from whichever ops [`Self::shuffle_set`]
+    /// hadn't marked done yet, without recomputing the permutation.
+    pub async fn resume_shuffle(
+        &self,
+        set_id: ObjectID,
+        shuffle_id: ulid::Ulid,
+    ) -> Result<(), SevenTvGqlError> {
+        self.execute_resumable(set_id, shuffle_id, None).await
+    }
 
-        for name in names {
-            if renamed.contains(name) {
-                continue;
-            }
-            let first = name;
-            ops.push((*emotes.get(first).unwrap(), Cow::Owned(gen_temp_name(16))));
-            let mut cur_target = name;
-            loop {
-                let original = map.get(cur_target).unwrap();
-                if *original == first {
-                    ops.push((*emotes.get(first).unwrap(), Cow::Borrowed(cur_target)));
-                    break;
-                }
-                if cur_target == *original {
-                    continue;
-                }
-                ops.push((*emotes.get(original).unwrap(), Cow::Borrowed(cur_target)));
-                cur_target = original;
-                renamed.insert(original);
+    /// Drives the renames for a previously recorded shuffle plan through a
+    /// bounded-concurrency worker pool: independent cycles run in parallel,
+    /// but ops within a single cycle stay strictly ordered, since a target
+    /// name can only be claimed once its current holder has vacated it.
+    async fn execute_resumable(
+        &self,
+        set_id: ObjectID,
+        shuffle_id: ulid::Ulid,
+        progress: Option<mpsc::Sender<ShuffleProgress>>,
+    ) -> Result<(), SevenTvGqlError> {
+        let pending = self.history().await?.pending_ops(set_id, shuffle_id).await?;
+        if pending.is_empty() {
+            if let Some(tx) = &progress {
+                let _ = tx.send(ShuffleProgress::Finished).await;
             }
+            return Ok(());
         }
 
-        let mut interval = tokio::time::interval(std::time::Duration::from_secs_f64(60.0 / 100.0));
-        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let total = pending.len();
+        let mut by_cycle: BTreeMap<i64, Vec<PendingOp>> = BTreeMap::new();
+        for op in pending {
+            by_cycle.entry(op.cycle_index).or_default().push(op);
+        }
 
-        let pb = ProgressBar::new(ops.len() as u64).with_style(
+        let limiter = self.limiter().await;
+        // with a progress channel the bar is hidden: it still counts for
+        // `ShuffleProgress::Renamed`, but nothing is printed
+        let draw_target = if progress.is_some() {
+            indicatif::ProgressDrawTarget::hidden()
+        } else {
+            indicatif::ProgressDrawTarget::stderr()
+        };
+        let pb = ProgressBar::with_draw_target(Some(total as u64), draw_target).with_style(
             ProgressStyle::with_template(
                 "{spinner} [{pos}/{len}] {bar:30.green/gray} ETA: {eta_precise:>}",
             )
             .unwrap()
             .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ "),
         );
-
         pb.enable_steady_tick(Duration::from_millis(100));
+        if let Some(tx) = &progress {
+            let _ = tx.send(ShuffleProgress::Started { total }).await;
+        }
 
-        for (source, target) in ops {
-            debug!("renaming {} to {}", source, target);
-            interval.tick().await;
-            self.rename_emote(set_id, source, target).await?;
-            pb.inc(1);
+        let mut remaining_cycles = by_cycle.into_values();
+        let mut in_flight = FuturesUnordered::new();
+        for cycle in remaining_cycles.by_ref().take(MAX_CONCURRENT_CYCLES) {
+            in_flight.push(self.run_cycle(set_id, shuffle_id, cycle, limiter, &pb, progress.as_ref(), total));
         }
+
+        while let Some(result) = in_flight.next().await {
+            result?;
+            if let Some(cycle) = remaining_cycles.next() {
+                in_flight.push(self.run_cycle(set_id, shuffle_id, cycle, limiter, &pb, progress.as_ref(), total));
+            }
+        }
+
         pb.finish();
+        if let Some(tx) = &progress {
+            let _ = tx.send(ShuffleProgress::Finished).await;
+        }
+
+        Ok(())
+    }
+
+    /// Runs every op in a single rename cycle strictly in order, marking
+    /// each as done so a later resume can skip it.
+    #[allow(clippy::too_many_arguments)]
+    async fn run_cycle(
+        &self,
+        set_id: ObjectID,
+        shuffle_id: ulid::Ulid,
+        cycle: Vec<PendingOp>,
+        limiter: &RateLimiter,
+        pb: &ProgressBar,
+        progress: Option<&mpsc::Sender<ShuffleProgress>>,
+        total: usize,
+    ) -> Result<(), SevenTvGqlError> {
+        for op in cycle {
+            let emote_id: ObjectID = op
+                .emote_id
+                .parse()
+                .expect("stored emote ids are valid ULIDs");
+
+            limiter.acquire().await;
+            debug!("renaming {} to {}", emote_id, op.target_name);
+            self.rename_emote(set_id, emote_id, op.target_name.clone())
+                .await?;
+            self.history()
+                .await?
+                .mark_op_done(set_id, shuffle_id, op.op_index)
+                .await?;
+            pb.inc(1);
 
+            if let Some(tx) = progress {
+                let _ = tx
+                    .send(ShuffleProgress::Renamed {
+                        done: pb.position() as usize,
+                        total,
+                        from: emote_id.to_string(),
+                        to: op.target_name,
+                    })
+                    .await;
+            }
+        }
         Ok(())
     }
+
+    /// Reverses a previous [`Self::shuffle_set`] call, renaming every emote
+    /// in `set_id` back to the name it had before `shuffle_id` was applied.
+    ///
+    /// Recorded as its own plan and driven through [`Self::execute_resumable`],
+    /// the same bounded-concurrency, rate-limited, resumable machinery
+    /// `shuffle_set` uses, so a restore interrupted mid-run can be resumed
+    /// with [`Self::resume_shuffle`] instead of leaving the set half-reverted.
+    pub async fn restore(
+        &self,
+        set_id: ObjectID,
+        shuffle_id: ulid::Ulid,
+    ) -> Result<(), SevenTvGqlError> {
+        let original_names = self.history().await?.get_shuffle(set_id, shuffle_id).await?;
+
+        let set = self.get_emote_set(set_id).await?;
+        let current_names: HashMap<ObjectID, String> =
+            set.emotes.iter().map(|e| (e.id, e.name.clone())).collect();
+
+        let cycles = compute_restore_ops(&current_names, &original_names);
+        if cycles.is_empty() {
+            return Ok(());
+        }
+
+        let restore_id = ulid::Ulid::new();
+        let owned_cycles: Vec<Vec<(ObjectID, String)>> = cycles
+            .iter()
+            .map(|cycle| {
+                cycle
+                    .iter()
+                    .map(|(id, target)| (*id, target.to_string()))
+                    .collect()
+            })
+            .collect();
+        self.history()
+            .await?
+            .record_shuffle(set_id, restore_id, &current_names, &owned_cycles)
+            .await?;
+
+        self.execute_resumable(set_id, restore_id, None).await
+    }
+
+    /// Lists past shuffles recorded for an emote set, most recent first.
+    pub async fn list_history(
+        &self,
+        set_id: ObjectID,
+    ) -> Result<Vec<ShuffleRecord>, SevenTvGqlError> {
+        Ok(self.history().await?.list_history(set_id).await?)
+    }
+}
+
+/// Computes the (source id, target name) renames needed to realize a random
+/// permutation of `set`'s emote names, grouped by independent rename cycle
+/// and broken with a temporary name so no two emotes ever collide on the
+/// same name mid-shuffle. Ops within a cycle must run in order; cycles
+/// themselves are independent of each other and can run in parallel.
+fn compute_shuffle_cycles(
+    set: &get_emote_set::GetEmoteSetEmoteSet,
+) -> Vec<Vec<(ObjectID, Cow<'_, str>)>> {
+    let mut names: Vec<&str> = set.emotes.iter().map(|e| e.name.as_str()).collect();
+    if names.is_empty() {
+        return Vec::new();
+    }
+    shuffle_slice(&mut names);
+
+    // target name is key, original name is value
+    let map: HashMap<&str, &str> = names
+        .iter()
+        .zip(&set.emotes)
+        .map(|(t, orig)| (*t, orig.name.as_str()))
+        .collect();
+
+    // maps original name to id
+    let emotes: HashMap<&str, ObjectID> =
+        set.emotes.iter().map(|e| (e.name.as_str(), e.id)).collect();
+
+    let mut cycles: Vec<Vec<(ObjectID, Cow<'_, str>)>> = Vec::new();
+    let mut renamed = HashSet::<&str>::with_capacity(map.len());
+
+    for name in names {
+        if renamed.contains(name) {
+            continue;
+        }
+        let first = name;
+        let mut cycle: Vec<(ObjectID, Cow<'_, str>)> =
+            vec![(*emotes.get(first).unwrap(), Cow::Owned(gen_temp_name(16)))];
+        let mut cur_target = name;
+        loop {
+            let original = map.get(cur_target).unwrap();
+            if *original == first {
+                cycle.push((*emotes.get(first).unwrap(), Cow::Borrowed(cur_target)));
+                break;
+            }
+            if cur_target == *original {
+                continue;
+            }
+            cycle.push((*emotes.get(original).unwrap(), Cow::Borrowed(cur_target)));
+            cur_target = original;
+            renamed.insert(original);
+        }
+        cycles.push(cycle);
+    }
+
+    cycles
+}
+
+/// Same cycle-breaking idea as [`compute_shuffle_cycles`], but driven by an
+/// explicit `current -> desired` name mapping instead of a fresh random
+/// permutation, so it can replay a recorded shuffle in reverse.
+///
+/// Groups into independent rename chains the same way
+/// [`compute_shuffle_cycles`] groups shuffle cycles, so a restore plan can be
+/// driven by the same resumable worker pool as a shuffle.
+fn compute_restore_ops(
+    current_names: &HashMap<ObjectID, String>,
+    desired_names: &HashMap<ObjectID, String>,
+) -> Vec<Vec<(ObjectID, Cow<'static, str>)>> {
+    // which id is assigned to move into a given (currently occupied) name
+    let assigned_to: HashMap<&str, ObjectID> = desired_names
+        .iter()
+        .map(|(id, name)| (name.as_str(), *id))
+        .collect();
+
+    let mut cycles: Vec<Vec<(ObjectID, Cow<'static, str>)>> = Vec::new();
+    let mut done = HashSet::<ObjectID>::with_capacity(desired_names.len());
+
+    for (&id, desired) in desired_names {
+        if done.contains(&id) || current_names.get(&id) == Some(desired) {
+            done.insert(id);
+            continue;
+        }
+
+        let first = id;
+        let mut cycle: Vec<(ObjectID, Cow<'static, str>)> =
+            vec![(first, Cow::Owned(gen_temp_name(16)))];
+        // the name just vacated by the previous move in the chain; whoever
+        // is assigned to move into it goes next
+        let mut freed_name = current_names[&first].clone();
+        loop {
+            match assigned_to.get(freed_name.as_str()) {
+                Some(&mover) if mover == first => {
+                    cycle.push((first, Cow::Owned(freed_name)));
+                    done.insert(first);
+                    break;
+                }
+                Some(&mover) => {
+                    cycle.push((mover, Cow::Owned(freed_name)));
+                    done.insert(mover);
+                    freed_name = current_names[&mover].clone();
+                }
+                None => break,
+            }
+        }
+        cycles.push(cycle);
+    }
+
+    cycles
+}
+
+#[cfg(test)]
+mod restore_ops_tests {
+    use super::*;
+
+    #[test]
+    fn three_cycle_restores_every_emote_to_its_original_name() {
+        let a = ObjectID::new();
+        let b = ObjectID::new();
+        let c = ObjectID::new();
+
+        // a shuffle put a -> "b", b -> "c", c -> "a"; restoring should bring
+        // each one back to its own original name.
+        let current_names = HashMap::from([
+            (a, "b".to_string()),
+            (b, "c".to_string()),
+            (c, "a".to_string()),
+        ]);
+        let desired_names = HashMap::from([
+            (a, "a".to_string()),
+            (b, "b".to_string()),
+            (c, "c".to_string()),
+        ]);
+
+        let cycles = compute_restore_ops(&current_names, &desired_names);
+        assert_eq!(cycles.len(), 1);
+
+        let mut names = current_names.clone();
+        for (id, target) in cycles.into_iter().flatten() {
+            for other in names.values() {
+                assert_ne!(
+                    other, &*target,
+                    "renamed into a name still held by another emote"
+                );
+            }
+            names.insert(id, target.into_owned());
+        }
+
+        assert_eq!(names[&a], "a");
+        assert_eq!(names[&b], "b");
+        assert_eq!(names[&c], "c");
+    }
 }
 
 type ObjectID = ulid::Ulid;
@@ -202,19 +580,11 @@ type ObjectID = ulid::Ulid;
 )]
 struct EmoteRename;
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-enum ConnectionPlatform {
-    Twitch,
-    Kick,
-    Youtube,
-    Discord,
-}
-
 #[derive(graphql_client::GraphQLQuery)]
 #[graphql(
     schema_path = "schemas/seventv.graphql",
-    query_path = "src/get_user_active_emote_set.graphql"
+    query_path = "src/get_user_active_emote_set.graphql",
+    response_derives = "PartialEq, Eq"
 )]
 struct GetUserActiveEmoteSet;
 
@@ -1,9 +1,14 @@
 use std::error::Error;
 
+#[cfg(not(feature = "gui"))]
 use clap::Parser;
-use emote_shuffler::{cli::Args, SevenTvGqlClient};
+#[cfg(not(feature = "gui"))]
+use emote_shuffler::cli::{Args, Command};
+use emote_shuffler::SevenTvGqlClient;
+#[cfg(not(feature = "gui"))]
 use log::error;
 
+#[cfg(not(feature = "gui"))]
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     env_logger::init();
@@ -13,10 +18,33 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let client = SevenTvGqlClient::new(dotenvy::var("SEVENTV_TOKEN").unwrap());
     let set = client.get_user_emote_set(&args.username).await?;
 
-    match client.shuffle_set(set.id).await {
-        Ok(_) => (),
-        Err(e) => error!("{e}"),
+    let result = match args.command {
+        Command::Shuffle => client.shuffle_set(set.id).await,
+        Command::Restore { shuffle_id } => client.restore(set.id, shuffle_id).await,
+        Command::History => {
+            for record in client.list_history(set.id).await? {
+                println!("{} (shuffled at {})", record.shuffle_id, record.created_at);
+            }
+            Ok(())
+        }
     };
 
+    if let Err(e) = result {
+        error!("{e}");
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "gui")]
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::init();
+
+    let rt = tokio::runtime::Runtime::new()?;
+    let _guard = rt.enter();
+
+    let client = SevenTvGqlClient::new(dotenvy::var("SEVENTV_TOKEN").unwrap());
+    emote_shuffler::gui::run(client)?;
+
     Ok(())
 }
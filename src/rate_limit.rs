@@ -0,0 +1,43 @@
+//! A small shared token bucket, so multiple concurrent workers can draw
+//! from the same rate limit instead of each keeping their own `interval`.
+
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::Semaphore;
+
+pub struct RateLimiter {
+    semaphore: Arc<Semaphore>,
+}
+
+impl RateLimiter {
+    /// Builds a limiter that allows `rate` acquisitions per minute on
+    /// average, refilling one token every `60 / rate` seconds. The bucket
+    /// starts full and holds up to `rate` tokens, so a burst of `rate`
+    /// acquisitions can go through at once on top of the steady refill.
+    pub fn per_minute(rate: u32) -> Self {
+        let semaphore = Arc::new(Semaphore::new(rate as usize));
+
+        let refill = semaphore.clone();
+        let period = Duration::from_secs_f64(60.0 / rate as f64);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if refill.available_permits() < rate as usize {
+                    refill.add_permits(1);
+                }
+            }
+        });
+
+        Self { semaphore }
+    }
+
+    pub async fn acquire(&self) {
+        self.semaphore
+            .acquire()
+            .await
+            .expect("rate limiter semaphore is never closed")
+            .forget();
+    }
+}
@@ -15,3 +15,10 @@ pub fn gen_temp_name(len: usize) -> String {
     let mut rng = rand::thread_rng();
     Alphanumeric {}.sample_string(&mut rng, len)
 }
+
+pub fn unix_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}